@@ -1,16 +1,18 @@
 use ar;
 use libflate::gzip::Decoder as GzDecoder;
+use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::fs::{self, File};
-use std::io::{self, BufRead, BufReader};
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
 use tar;
 use xz2::read::XzDecoder;
 
 pub struct Archive<'a> {
     path: &'a Path,
     data: (u8, Codec),
-    control: (u8, Codec)
+    control: (u8, Codec),
+    data_index: RefCell<Option<BTreeMap<PathBuf, (u64, u64)>>>
 }
 
 impl<'a> Archive<'a> {
@@ -54,7 +56,7 @@ impl<'a> Archive<'a> {
             format!("control archive not found in {}", path.display())
         ))?;
 
-        Ok(Archive { path, control, data })
+        Ok(Archive { path, control, data, data_index: RefCell::new(None) })
     }
 
     /// Enables the caller to process entries from the inner control archive.
@@ -65,9 +67,16 @@ impl<'a> Archive<'a> {
         ))
     }
 
-    /// Unpacks the inner control archive to the given path.
+    /// Unpacks the inner control archive to the given path, preserving the exact behavior this
+    /// method had before `ExtractOptions` existed. Use `control_extract_with` to opt into the
+    /// safer defaults in `ExtractOptions::default`.
     pub fn control_extract<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        self.extract(path, self.control.0, self.control.1)
+        self.control_extract_with(path, &ExtractOptions::legacy())
+    }
+
+    /// Unpacks the inner control archive to the given path, honoring the given `ExtractOptions`.
+    pub fn control_extract_with<P: AsRef<Path>>(&self, path: P, options: &ExtractOptions) -> io::Result<()> {
+        self.extract(path, self.control.0, self.control.1, options)
     }
 
     // Enables the caller to get the contents of the control file in the control archive as a map
@@ -78,6 +87,31 @@ impl<'a> Archive<'a> {
         ))
     }
 
+    /// Gets the paths listed in the control archive's `conffiles` member, one per line.
+    /// Returns an empty `Vec` if the package has no `conffiles` member.
+    pub fn conffiles(&self) -> io::Result<Vec<PathBuf>> {
+        self.inner_conffiles().map_err(|why| io::Error::other(
+            format!("error reading conffiles from control archive within {}: {}", self.path.display(), why)
+        ))
+    }
+
+    /// Gets the contents of the control archive's `md5sums` member, keyed by the path of each
+    /// data file relative to the filesystem root. Returns an empty map if the package has no
+    /// `md5sums` member.
+    pub fn md5sums(&self) -> io::Result<BTreeMap<PathBuf, String>> {
+        self.inner_md5sums().map_err(|why| io::Error::other(
+            format!("error reading md5sums from control archive within {}: {}", self.path.display(), why)
+        ))
+    }
+
+    /// Gets the contents of the given maintainer script from the control archive, or `None` if
+    /// the package does not carry one.
+    pub fn maintainer_script(&self, which: Script) -> io::Result<Option<String>> {
+        self.inner_control_member(which.member_name()).map_err(|why| io::Error::other(
+            format!("error reading {} from control archive within {}: {}", which.member_name(), self.path.display(), why)
+        ))
+    }
+
     /// Enables the caller to process entries from the inner data archive.
     pub fn data<F: FnMut(&mut tar::Entry<&mut dyn io::Read>) -> io::Result<()>>(&self, action: F) -> io::Result<()> {
         self.inner_data(action).map_err(|why| io::Error::new(
@@ -86,9 +120,39 @@ impl<'a> Archive<'a> {
         ))
     }
 
-    /// Unpacks the inner data archive to the given path.
+    /// Unpacks the inner data archive to the given path, preserving the exact behavior this
+    /// method had before `ExtractOptions` existed. Use `data_extract_with` to opt into the safer
+    /// defaults in `ExtractOptions::default`.
     pub fn data_extract<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        self.extract(path, self.data.0, self.data.1)
+        self.data_extract_with(path, &ExtractOptions::legacy())
+    }
+
+    /// Unpacks the inner data archive to the given path, honoring the given `ExtractOptions`.
+    pub fn data_extract_with<P: AsRef<Path>>(&self, path: P, options: &ExtractOptions) -> io::Result<()> {
+        self.extract(path, self.data.0, self.data.1, options)
+    }
+
+    /// Reads a single file out of the data archive by its path within the tar, without walking
+    /// every entry that precedes it.
+    ///
+    /// The first call builds an index of every member's offset and size within the decompressed
+    /// tar stream and caches it on `self`; later calls reuse it.
+    pub fn read_file<P: AsRef<Path>>(&self, member: P) -> io::Result<Vec<u8>> {
+        self.inner_read_file(member.as_ref()).map_err(|why| io::Error::other(
+            format!("error reading {} from data archive within {}: {}", member.as_ref().display(), self.path.display(), why)
+        ))
+    }
+
+    /// Extracts a single file out of the data archive to `dst`, by its path within the tar.
+    pub fn extract_file<M: AsRef<Path>, D: AsRef<Path>>(&self, member: M, dst: D) -> io::Result<()> {
+        let dst = dst.as_ref();
+        let data = self.read_file(member)?;
+
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(dst, data)
     }
 
     fn open_archive<F, T>(&self, id: u8, codec: Codec, mut func: F) -> io::Result<T>
@@ -128,13 +192,90 @@ impl<'a> Archive<'a> {
         self.iter_entries(action, self.control.0, self.control.1)
     }
 
-    fn extract<P: AsRef<Path>>(&self, path: P, id: u8, codec: Codec) -> io::Result<()> {
+    /// Builds the `path -> (data_start_offset, size)` index for the data archive if it hasn't
+    /// been built yet. The offsets are into the *decompressed* tar stream, so the index must be
+    /// rebuilt from the decoder output rather than the compressed `ar` member.
+    fn data_index(&self) -> io::Result<()> {
+        if self.data_index.borrow().is_some() {
+            return Ok(());
+        }
+
+        let index = self.open_archive(self.data.0, self.data.1, |reader| -> io::Result<BTreeMap<PathBuf, (u64, u64)>> {
+            let mut index = BTreeMap::new();
+
+            for entry in tar::Archive::new(reader).entries()? {
+                let entry = entry?;
+                if entry.header().entry_type().is_dir() {
+                    continue
+                }
+
+                let path = normalize_member_path(&entry.path()?);
+                let size = entry.header().size()?;
+                index.insert(path, (entry.raw_file_position(), size));
+            }
+
+            Ok(index)
+        })??;
+
+        *self.data_index.borrow_mut() = Some(index);
+        Ok(())
+    }
+
+    fn inner_read_file(&self, member: &Path) -> io::Result<Vec<u8>> {
+        self.data_index()?;
+
+        let member = normalize_member_path(member);
+        let (offset, size) = {
+            let index = self.data_index.borrow();
+            *index.as_ref().unwrap().get(&member).ok_or_else(|| io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found in data archive", member.display())
+            ))?
+        };
+
+        self.open_archive(self.data.0, self.data.1, |reader| -> io::Result<Vec<u8>> {
+            // xz/gz/zstd streams aren't seekable, so the bytes before the member have to be
+            // read and discarded rather than skipped.
+            let mut discard = [0u8; 8192];
+            let mut remaining = offset;
+            while remaining > 0 {
+                let chunk = remaining.min(discard.len() as u64) as usize;
+                reader.read_exact(&mut discard[..chunk])?;
+                remaining -= chunk as u64;
+            }
+
+            let mut buffer = vec![0; size as usize];
+            reader.read_exact(&mut buffer)?;
+            Ok(buffer)
+        })?
+    }
+
+    fn extract<P: AsRef<Path>>(&self, path: P, id: u8, codec: Codec, options: &ExtractOptions) -> io::Result<()> {
         let path = path.as_ref();
         if !path.exists() {
             fs::create_dir_all(path)?;
         }
 
-        self.open_archive(id, codec, |reader| tar::Archive::new(reader).unpack(path))?
+        self.open_archive(id, codec, |reader| -> io::Result<()> {
+            let mut archive = tar::Archive::new(reader);
+            archive.set_unpack_xattrs(options.unpack_xattrs);
+            archive.set_preserve_permissions(options.preserve_permissions);
+            archive.set_preserve_mtime(options.preserve_mtime);
+            archive.set_overwrite(options.overwrite);
+            archive.set_ignore_zeros(options.ignore_zeros);
+
+            if !options.sanitize_paths {
+                return archive.unpack(path);
+            }
+
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                let dest = sanitize_path(&entry.path()?, path)?;
+                entry.unpack(dest)?;
+            }
+
+            Ok(())
+        })?
     }
 
     fn inner_control_map(&self) -> io::Result<BTreeMap<String, String>> {
@@ -188,11 +329,190 @@ impl<'a> Archive<'a> {
             Ok(control_data)
         })?
     }
+
+    fn inner_conffiles(&self) -> io::Result<Vec<PathBuf>> {
+        match self.inner_control_member("conffiles")? {
+            Some(contents) => Ok(contents.lines().map(PathBuf::from).collect()),
+            None => Ok(Vec::new())
+        }
+    }
+
+    fn inner_md5sums(&self) -> io::Result<BTreeMap<PathBuf, String>> {
+        let mut md5sums = BTreeMap::new();
+
+        if let Some(contents) = self.inner_control_member("md5sums")? {
+            for line in contents.lines() {
+                if let Some(pos) = line.find("  ") {
+                    let (hash, path) = line.split_at(pos);
+                    md5sums.insert(PathBuf::from(path[2..].trim()), hash.to_owned());
+                }
+            }
+        }
+
+        Ok(md5sums)
+    }
+
+    /// Finds a well-known member of the control archive by name, matching both the `./name`
+    /// and bare `name` forms that different `dpkg-deb` versions have produced, and returns its
+    /// contents as a string.
+    fn inner_control_member(&self, name: &str) -> io::Result<Option<String>> {
+        let (id, codec) = (self.control.0, self.control.1);
+        self.open_archive(id, codec, |reader| -> io::Result<Option<String>> {
+            let rooted = format!("./{}", name);
+
+            for entry in tar::Archive::new(reader).entries()? {
+                let mut entry = entry?;
+                let path = entry.path()?.to_path_buf();
+
+                if path == Path::new(name) || path == Path::new(&rooted) {
+                    let mut contents = String::new();
+                    entry.read_to_string(&mut contents)?;
+                    return Ok(Some(contents));
+                }
+            }
+
+            Ok(None)
+        })?
+    }
 }
 
+/// A Debian maintainer script, carried as a member of the control archive.
 #[derive(Copy, Clone, Debug)]
-enum Codec {
+pub enum Script {
+    PreInst,
+    PostInst,
+    PreRm,
+    PostRm
+}
+
+impl Script {
+    fn member_name(self) -> &'static str {
+        match self {
+            Script::PreInst => "preinst",
+            Script::PostInst => "postinst",
+            Script::PreRm => "prerm",
+            Script::PostRm => "postrm",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Codec {
     Xz,
     Gz,
     Zstd
 }
+
+impl Codec {
+    /// The file extension that `dpkg-deb` appends to a `control.tar` or `data.tar` member
+    /// compressed with this codec.
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Codec::Xz => "xz",
+            Codec::Gz => "gz",
+            Codec::Zstd => "zst",
+        }
+    }
+}
+
+/// Controls how `Archive::data_extract_with`/`control_extract_with` apply metadata and guard
+/// against unsafe paths while unpacking a tar archive.
+///
+/// Note that `sanitize_paths` only normalizes and bounds-checks each entry's own path; it does
+/// not inspect the *target* of a symlink or hard link entry, which `tar`'s unpacking can still
+/// write pointing outside of the destination directory (or at an absolute path). Don't rely on
+/// `sanitize_paths` alone to extract an archive from a fully untrusted source if it may contain
+/// such entries.
+#[derive(Copy, Clone, Debug)]
+pub struct ExtractOptions {
+    /// Whether to unpack extended attributes. Defaults to `false`.
+    pub unpack_xattrs: bool,
+    /// Whether to restore the original Unix permissions of each entry. Defaults to `true`.
+    pub preserve_permissions: bool,
+    /// Whether to restore the original modification time of each entry. Defaults to `true`.
+    pub preserve_mtime: bool,
+    /// Whether to overwrite existing files and directories at the destination. Defaults to
+    /// `true`.
+    pub overwrite: bool,
+    /// Whether to ignore zeroed headers, which would otherwise signal the end of the archive.
+    /// Defaults to `false`.
+    pub ignore_zeros: bool,
+    /// Whether to normalize each entry's path and reject any that would resolve outside of the
+    /// destination directory before writing it, guarding against a malicious `../` or absolute
+    /// path in an untrusted archive. Defaults to `true`.
+    pub sanitize_paths: bool
+}
+
+impl Default for ExtractOptions {
+    /// The recommended defaults for extracting an archive from an untrusted source.
+    fn default() -> Self {
+        ExtractOptions {
+            unpack_xattrs: false,
+            preserve_permissions: true,
+            preserve_mtime: true,
+            overwrite: true,
+            ignore_zeros: false,
+            sanitize_paths: true
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// The options that reproduce the behavior `Archive::data_extract`/`control_extract` had
+    /// before `ExtractOptions` existed: a plain `tar::Archive::unpack` with no path
+    /// sanitization. Kept so those two methods don't change behavior for existing callers; new
+    /// code should prefer `ExtractOptions::default` or the `_with` methods explicitly.
+    pub fn legacy() -> Self {
+        ExtractOptions {
+            unpack_xattrs: false,
+            preserve_permissions: false,
+            preserve_mtime: true,
+            overwrite: true,
+            ignore_zeros: false,
+            sanitize_paths: false
+        }
+    }
+}
+
+/// Strips any leading `./`/`/` components from a tar member path, the same way
+/// `inner_control_member` matches both the `./name` and bare `name` forms that different
+/// `dpkg-deb` versions produce, so the data index can be looked up regardless of which form the
+/// archive or the caller used.
+fn normalize_member_path(path: &Path) -> PathBuf {
+    let mut components = path.components().peekable();
+
+    while let Some(Component::CurDir) | Some(Component::RootDir) = components.peek() {
+        components.next();
+    }
+
+    components.collect()
+}
+
+/// Normalizes `entry_path` against `dest`, stripping any leading root component and rejecting
+/// the entry if a `..` component would walk it outside of `dest`.
+///
+/// This only validates the entry's own path. A symlink or hard link entry whose *target* is
+/// absolute or escapes `dest` is not checked here; `tar`'s unpacking will still write it as-is.
+fn sanitize_path(entry_path: &Path, dest: &Path) -> io::Result<PathBuf> {
+    let mut parts: Vec<&std::ffi::OsStr> = Vec::new();
+
+    for component in entry_path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => {
+                if parts.pop().is_none() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("archive entry {} escapes the destination directory", entry_path.display())
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(parts.into_iter().fold(dest.to_path_buf(), |mut sanitized, part| {
+        sanitized.push(part);
+        sanitized
+    }))
+}
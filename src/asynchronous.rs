@@ -0,0 +1,218 @@
+use crate::archive::Codec;
+use async_compression::tokio::bufread::{GzipDecoder, XzDecoder, ZstdDecoder};
+use std::collections::BTreeMap;
+use std::io::{self, Seek, SeekFrom};
+use std::path::Path;
+use std::pin::Pin;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, BufReader};
+use tokio_stream::StreamExt;
+
+type Decoder = Pin<Box<dyn AsyncRead + Send>>;
+
+/// An async counterpart to [`Archive`](crate::Archive), built on `tokio-tar`.
+///
+/// It mirrors `Archive`'s `data`, `control`, `control_map`, `data_extract` and
+/// `control_extract`, but each of them returns a future instead of blocking the calling thread
+/// on decompression and tar parsing -- useful for a package-management daemon unpacking many
+/// `.deb`s concurrently. The extract methods take no `ExtractOptions` and always unpack with
+/// `tokio-tar`'s defaults; there are no async counterparts of `read_file`, `extract_file`,
+/// `conffiles`, `md5sums` or `maintainer_script`.
+pub struct AsyncArchive<'a> {
+    path: &'a Path,
+    data: (u64, Codec),
+    control: (u64, Codec)
+}
+
+impl<'a> AsyncArchive<'a> {
+    /// The path given must be a valid Debian ar archive. The `ar` header is scanned
+    /// synchronously, the same way [`Archive::new`](crate::Archive::new) does, since it is a
+    /// handful of small fixed-size headers; only the tar/codec layer is async.
+    pub fn new(path: &'a Path) -> io::Result<Self> {
+        let mut archive = ar::Archive::new(std::fs::File::open(path)?);
+
+        let mut control = None;
+        let mut data = None;
+        let mut entry_id = 0;
+
+        while let Some(entry_result) = archive.next_entry() {
+            if let Ok(entry) = entry_result {
+                match entry.header().identifier() {
+                    b"data.tar.xz" => data = Some((entry_id, Codec::Xz)),
+                    b"data.tar.gz" => data = Some((entry_id, Codec::Gz)),
+                    b"data.tar.zst" => data = Some((entry_id, Codec::Zstd)),
+                    b"control.tar.xz" => control = Some((entry_id, Codec::Xz)),
+                    b"control.tar.gz" => control = Some((entry_id, Codec::Gz)),
+                    b"control.tar.zst" => control = Some((entry_id, Codec::Zstd)),
+                    _ => {
+                        entry_id += 1;
+                        continue
+                    }
+                }
+
+                if data.is_some() && control.is_some() { break }
+            }
+
+            entry_id += 1;
+        }
+
+        let (data_id, data_codec) = data.ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("data archive not found in {}", path.display())
+        ))?;
+
+        let (control_id, control_codec) = control.ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("control archive not found in {}", path.display())
+        ))?;
+
+        let data = (Self::member_offset(path, data_id)?, data_codec);
+        let control = (Self::member_offset(path, control_id)?, control_codec);
+
+        Ok(AsyncArchive { path, data, control })
+    }
+
+    /// Enables the caller to process entries from the inner control archive.
+    pub async fn control<F>(&self, action: F) -> io::Result<()>
+        where F: FnMut(&mut tokio_tar::Entry<Decoder>) -> io::Result<()>
+    {
+        self.iter_entries(self.control.0, self.control.1, action).await.map_err(|why| io::Error::other(
+            format!("error reading control archive within {}: {}", self.path.display(), why)
+        ))
+    }
+
+    /// Unpacks the inner control archive to the given path.
+    pub async fn control_extract<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.extract(self.control.0, self.control.1, path).await
+    }
+
+    /// Enables the caller to get the contents of the control file in the control archive as a
+    /// map.
+    pub async fn control_map(&self) -> io::Result<BTreeMap<String, String>> {
+        self.inner_control_map().await.map_err(|why| io::Error::other(
+            format!("error reading control archive within {}: {}", self.path.display(), why)
+        ))
+    }
+
+    /// Enables the caller to process entries from the inner data archive.
+    pub async fn data<F>(&self, action: F) -> io::Result<()>
+        where F: FnMut(&mut tokio_tar::Entry<Decoder>) -> io::Result<()>
+    {
+        self.iter_entries(self.data.0, self.data.1, action).await.map_err(|why| io::Error::other(
+            format!("error reading data archive within {}: {}", self.path.display(), why)
+        ))
+    }
+
+    /// Unpacks the inner data archive to the given path.
+    pub async fn data_extract<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.extract(self.data.0, self.data.1, path).await
+    }
+
+    /// Seeks a plain, synchronous `File` to the start of the ar member's data, reusing `ar`'s
+    /// jump rather than re-deriving the ar layout by hand.
+    ///
+    /// The position has to be read back from `file` itself, not from the `Entry` that
+    /// `jump_to_entry` returns: `Entry`'s own `Seek` impl tracks a position relative to the
+    /// start of the entry (always `0` right after the jump), not the archive-absolute offset
+    /// that `open_archive` needs to seek the async file handle to.
+    fn member_offset(path: &Path, id: u8) -> io::Result<u64> {
+        let mut file = std::fs::File::open(path)?;
+
+        {
+            let mut archive = ar::Archive::new(&mut file);
+            archive.jump_to_entry(id as usize)?;
+        }
+
+        file.stream_position()
+    }
+
+    async fn open_archive(&self, offset: u64, codec: Codec) -> io::Result<Decoder> {
+        let mut file = File::open(self.path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        let reader = BufReader::new(file);
+
+        Ok(match codec {
+            Codec::Xz => Box::pin(XzDecoder::new(reader)),
+            Codec::Gz => Box::pin(GzipDecoder::new(reader)),
+            Codec::Zstd => Box::pin(ZstdDecoder::new(reader)),
+        })
+    }
+
+    async fn iter_entries<F>(&self, offset: u64, codec: Codec, mut action: F) -> io::Result<()>
+        where F: FnMut(&mut tokio_tar::Entry<Decoder>) -> io::Result<()>
+    {
+        let reader = self.open_archive(offset, codec).await?;
+        let mut archive = tokio_tar::Archive::new(reader);
+        let mut entries = archive.entries()?;
+
+        while let Some(entry) = entries.next().await {
+            let mut entry = entry?;
+            if entry.header().entry_type().is_dir() {
+                continue
+            }
+
+            action(&mut entry)?;
+        }
+
+        Ok(())
+    }
+
+    async fn extract<P: AsRef<Path>>(&self, offset: u64, codec: Codec, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if !path.exists() {
+            tokio::fs::create_dir_all(path).await?;
+        }
+
+        let reader = self.open_archive(offset, codec).await?;
+        tokio_tar::Archive::new(reader).unpack(path).await
+    }
+
+    async fn inner_control_map(&self) -> io::Result<BTreeMap<String, String>> {
+        let reader = self.open_archive(self.control.0, self.control.1).await?;
+        let mut archive = tokio_tar::Archive::new(reader);
+        let mut entries = archive.entries()?;
+        let mut control_data = BTreeMap::new();
+
+        while let Some(entry) = entries.next().await {
+            let mut entry = entry?;
+            let path = entry.path()?.to_path_buf();
+
+            if path == Path::new("./control") || path == Path::new("control") {
+                let mut contents = String::new();
+                entry.read_to_string(&mut contents).await?;
+                fold_control_paragraph(&contents, &mut control_data);
+            }
+        }
+
+        Ok(control_data)
+    }
+}
+
+/// Parses a control paragraph's contents into `key -> value`, folding a multi-line
+/// `Description` the same way `Archive::control_map` does for the synchronous reader.
+fn fold_control_paragraph(contents: &str, control_data: &mut BTreeMap<String, String>) {
+    let mut lines = contents.lines().peekable();
+    let mut description_unset = true;
+
+    while let Some(line) = lines.next() {
+        if let Some(pos) = line.find(':') {
+            let (key, value) = line.split_at(pos);
+            let mut value = value[1..].trim().to_owned();
+
+            if description_unset && key == "Description" {
+                description_unset = false;
+                while let Some(next_line) = lines.peek() {
+                    if next_line.starts_with(' ') {
+                        value.push('\n');
+                        value.push_str(next_line);
+                        lines.next();
+                    } else {
+                        break
+                    }
+                }
+            }
+
+            control_data.insert(key.to_owned(), value);
+        }
+    }
+}
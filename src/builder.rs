@@ -0,0 +1,307 @@
+use crate::archive::Codec;
+use libflate::gzip::Encoder as GzEncoder;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use xz2::write::XzEncoder;
+
+/// Assembles a new `.deb` archive from a set of control fields and data files.
+///
+/// The resulting archive is a valid Debian `ar` archive containing a `debian-binary` member,
+/// a `control.tar.<codec>`, and a `data.tar.<codec>` -- the inverse of what [`Archive`] reads.
+///
+/// [`Archive`]: crate::Archive
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use debarchive::{Builder, Codec};
+/// use std::path::Path;
+///
+/// let mut builder = Builder::new();
+/// builder
+///     .control_field("Package", "example")
+///     .control_field("Version", "1.0.0")
+///     .control_field("Architecture", "amd64")
+///     .control_field("Maintainer", "Example <example@example.org>")
+///     .control_field("Description", "An example package")
+///     .data_codec(Codec::Zstd)
+///     .add_file("usr/bin/example", "target/release/example", 0o100755);
+///
+/// builder.build(Path::new("example_1.0.0_amd64.deb")).unwrap();
+/// ```
+pub struct Builder {
+    control: BTreeMap<String, String>,
+    control_codec: Codec,
+    data_codec: Codec,
+    entries: Vec<DataEntry>,
+}
+
+enum DataEntry {
+    File { dest: PathBuf, source: PathBuf, mode: u32 },
+    Directory { dest: PathBuf, mode: u32 },
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder {
+            control: BTreeMap::new(),
+            control_codec: Codec::Xz,
+            data_codec: Codec::Xz,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl Builder {
+    /// Creates an empty builder. Both the control and data archives default to `Codec::Xz`,
+    /// matching the format that `dpkg-deb` produces.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the compression codec used for the `control.tar.<codec>` member.
+    pub fn control_codec(&mut self, codec: Codec) -> &mut Self {
+        self.control_codec = codec;
+        self
+    }
+
+    /// Sets the compression codec used for the `data.tar.<codec>` member.
+    pub fn data_codec(&mut self, codec: Codec) -> &mut Self {
+        self.data_codec = codec;
+        self
+    }
+
+    /// Sets a field of the control file, such as `Package` or `Version`.
+    ///
+    /// Fields are serialized in Debian control paragraph format. A multi-line `Description`
+    /// is folded back into the indented continuation-line form that `Archive::control_map`
+    /// unfolds when reading.
+    pub fn control_field<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> &mut Self {
+        self.control.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds the file at `source`, on the local filesystem, to `dest` within the data archive.
+    pub fn add_file<D: Into<PathBuf>, S: Into<PathBuf>>(&mut self, dest: D, source: S, mode: u32) -> &mut Self {
+        self.entries.push(DataEntry::File { dest: dest.into(), source: source.into(), mode });
+        self
+    }
+
+    /// Adds an empty directory entry to the data archive.
+    pub fn add_directory<D: Into<PathBuf>>(&mut self, dest: D, mode: u32) -> &mut Self {
+        self.entries.push(DataEntry::Directory { dest: dest.into(), mode });
+        self
+    }
+
+    /// Writes the assembled archive to `path`.
+    pub fn build<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.inner_build(path.as_ref()).map_err(|why| io::Error::other(
+            format!("error building archive at {}: {}", path.as_ref().display(), why)
+        ))
+    }
+
+    fn inner_build(&self, path: &Path) -> io::Result<()> {
+        let (data_tar, md5sums) = self.build_data_tar()?;
+        let control_tar = self.build_control_tar(&md5sums)?;
+
+        let mut archive = ar::Builder::new(File::create(path)?);
+
+        archive.append(&ar::Header::new(b"debian-binary".to_vec(), 4), &b"2.0\n"[..])?;
+
+        let name = format!("control.tar.{}", self.control_codec.extension()).into_bytes();
+        archive.append(&ar::Header::new(name, control_tar.len() as u64), control_tar.as_slice())?;
+
+        let name = format!("data.tar.{}", self.data_codec.extension()).into_bytes();
+        archive.append(&ar::Header::new(name, data_tar.len() as u64), data_tar.as_slice())?;
+
+        Ok(())
+    }
+
+    /// Builds `data.tar.<codec>`, returning its bytes along with the md5sum of every file
+    /// added, keyed by its destination path.
+    fn build_data_tar(&self) -> io::Result<(Vec<u8>, BTreeMap<PathBuf, String>)> {
+        let mut md5sums = BTreeMap::new();
+        let mut tar = tar::Builder::new(Encoder::new(self.data_codec)?);
+
+        for entry in &self.entries {
+            match entry {
+                DataEntry::Directory { dest, mode } => {
+                    let mut header = tar::Header::new_gnu();
+                    header.set_entry_type(tar::EntryType::Directory);
+                    header.set_mode(*mode);
+                    header.set_size(0);
+                    header.set_cksum();
+                    tar.append_data(&mut header, dest, io::empty())?;
+                }
+                DataEntry::File { dest, source, mode } => {
+                    let data = fs::read(source)?;
+                    md5sums.insert(dest.clone(), format!("{:x}", md5::compute(&data)));
+
+                    let mut header = tar::Header::new_gnu();
+                    header.set_mode(*mode);
+                    header.set_size(data.len() as u64);
+                    header.set_cksum();
+                    tar.append_data(&mut header, dest, data.as_slice())?;
+                }
+            }
+        }
+
+        let data_tar = tar.into_inner()?.finish()?;
+        Ok((data_tar, md5sums))
+    }
+
+    /// Builds `control.tar.<codec>`, containing the `control` and `md5sums` members.
+    fn build_control_tar(&self, md5sums: &BTreeMap<PathBuf, String>) -> io::Result<Vec<u8>> {
+        let control = self.format_control();
+        let md5sums = format_md5sums(md5sums);
+
+        let mut tar = tar::Builder::new(Encoder::new(self.control_codec)?);
+        append_buffer(&mut tar, "./control", control.as_bytes())?;
+        append_buffer(&mut tar, "./md5sums", md5sums.as_bytes())?;
+        tar.into_inner()?.finish()
+    }
+
+    /// Serializes the control fields in Debian control paragraph format, folding a multi-line
+    /// `Description` the same way `Archive::control_map` unfolds one.
+    fn format_control(&self) -> String {
+        let mut out = String::new();
+
+        for (key, value) in &self.control {
+            let mut lines = value.split('\n');
+
+            out.push_str(key);
+            out.push_str(": ");
+            out.push_str(lines.next().unwrap_or(""));
+            out.push('\n');
+
+            for line in lines {
+                if line.is_empty() {
+                    out.push_str(" .");
+                } else {
+                    out.push(' ');
+                    out.push_str(line);
+                }
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+}
+
+fn format_md5sums(entries: &BTreeMap<PathBuf, String>) -> String {
+    let mut out = String::new();
+    for (path, hash) in entries {
+        out.push_str(hash);
+        out.push_str("  ");
+        out.push_str(&path.display().to_string());
+        out.push('\n');
+    }
+
+    out
+}
+
+fn append_buffer<W: io::Write>(tar: &mut tar::Builder<W>, name: &str, data: &[u8]) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(0o644);
+    header.set_size(data.len() as u64);
+    header.set_cksum();
+    tar.append_data(&mut header, name, data)
+}
+
+/// A `Write` sink that compresses into an in-memory buffer with whichever codec was chosen,
+/// so the same `tar::Builder` call sites work regardless of which one is selected.
+enum Encoder {
+    Xz(XzEncoder<Vec<u8>>),
+    Gz(GzEncoder<Vec<u8>>),
+    Zstd(zstd::Encoder<'static, Vec<u8>>),
+}
+
+impl Encoder {
+    fn new(codec: Codec) -> io::Result<Self> {
+        Ok(match codec {
+            Codec::Xz => Encoder::Xz(XzEncoder::new(Vec::new(), 6)),
+            Codec::Gz => Encoder::Gz(GzEncoder::new(Vec::new())?),
+            Codec::Zstd => Encoder::Zstd(zstd::Encoder::new(Vec::new(), 0)?),
+        })
+    }
+
+    /// Flushes the compressor and returns the compressed bytes.
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Encoder::Xz(encoder) => encoder.finish().map_err(io::Error::other),
+            Encoder::Gz(encoder) => encoder.finish().into_result(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Xz(encoder) => encoder.write(buf),
+            Encoder::Gz(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Xz(encoder) => encoder.flush(),
+            Encoder::Gz(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Archive;
+
+    /// Builds a small `.deb` with one data file and a folded `Description`, then reads it back
+    /// through `Archive` to check that the control paragraph, the generated `md5sums`, and the
+    /// data file itself all round-trip.
+    #[test]
+    fn builder_archive_round_trip() {
+        let dir = std::env::temp_dir().join(format!("debarchive-builder-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let source = dir.join("hello.txt");
+        fs::write(&source, b"hello, world\n").unwrap();
+
+        let deb_path = dir.join("test_1.0.0_amd64.deb");
+
+        Builder::new()
+            .control_field("Package", "test")
+            .control_field("Version", "1.0.0")
+            .control_field("Architecture", "amd64")
+            .control_field("Description", "An example package\nwith a folded line")
+            .add_file("usr/share/test/hello.txt", source, 0o100644)
+            .build(&deb_path)
+            .unwrap();
+
+        let archive = Archive::new(&deb_path).unwrap();
+
+        let control = archive.control_map().unwrap();
+        assert_eq!(control.get("Package").map(String::as_str), Some("test"));
+        assert_eq!(control.get("Version").map(String::as_str), Some("1.0.0"));
+        // `Archive::control_map` keeps the mandatory fold-marker space that `format_control`
+        // prefixes onto each continuation line as part of the unfolded value.
+        assert_eq!(
+            control.get("Description").map(String::as_str),
+            Some("An example package\n with a folded line")
+        );
+
+        let md5sums = archive.md5sums().unwrap();
+        let expected_md5 = format!("{:x}", md5::compute(b"hello, world\n"));
+        assert_eq!(md5sums.get(Path::new("usr/share/test/hello.txt")), Some(&expected_md5));
+
+        let contents = archive.read_file(Path::new("usr/share/test/hello.txt")).unwrap();
+        assert_eq!(contents, b"hello, world\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
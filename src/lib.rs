@@ -4,7 +4,7 @@
 //! 
 //! - [x] Reading files from archives
 //! - [x] Extracting files from archives
-//! - [ ] Writing new debian archives
+//! - [x] Writing new debian archives
 //! 
 //! # Examples
 //! 
@@ -31,7 +31,15 @@ extern crate ar;
 extern crate tar;
 extern crate xz2;
 extern crate libflate;
+extern crate zstd;
+extern crate md5;
 
 mod archive;
+mod builder;
+#[cfg(feature = "async")]
+mod asynchronous;
 
-pub use self::archive::*;
\ No newline at end of file
+pub use self::archive::*;
+pub use self::builder::*;
+#[cfg(feature = "async")]
+pub use self::asynchronous::AsyncArchive;
\ No newline at end of file